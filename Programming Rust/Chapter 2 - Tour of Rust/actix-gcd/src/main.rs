@@ -1,11 +1,39 @@
 // Each of the names inside the curly brackets becomes usable
 // in our code. That way we dont have to type "actix_web::HttpServer"
 // each time we want to use the HttpServer command.
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::{web, App, Error, HttpResponse, HttpServer};
 
 // Serde: Process the form data
 use serde::Deserialize;
 
+// The Mandelbrot renderer. Rather than keeping a second, hand-copied version
+// of the renderer that could quietly drift out of sync with the original,
+// this includes the exact same source file the standalone `mandelbrot`
+// program builds from its own `render` module.
+#[path = "../../mandelbrot/src/render.rs"]
+mod render;
+
+use num::Complex;
+use image::ColorType;
+use image::png::PNGEncoder;
+use std::io::Cursor;
+use futures::future::{self, Either, Future};
+
+// Largest tile we're willing to render for a single request, so a client
+// can't ask for a buffer large enough to exhaust the server's memory.
+const MAX_TILE_DIMENSION: u32 = 4096;
+
+// Largest iteration limit we're willing to run per pixel, on its own.
+const MAX_ITERATION_LIMIT: usize = 10_000;
+
+// Largest *combined* cost we're willing to render for a single request,
+// counted in escape-time steps (roughly `width * height * limit`). Bounding
+// width, height and limit individually isn't enough on its own: a maxed-out
+// tile paired with a maxed-out iteration limit is still ~1.6x10^11 steps,
+// easily enough to pin a worker thread for a very long time. This cap is
+// well below that product.
+const MAX_RENDER_WORK: u64 = 50_000_000;
+
 // Structure that represents the values we expect from the form
 // (The attribute tells Serde to examine the type "GcdParameters" when the program 
 //  is compiled and automatically generate code to parse a value of this type
@@ -36,6 +64,7 @@ fn main() {
         App::new()
             .route("/", web::get().to(get_index))
             .route("/gcd", web::post().to(post_gcd))
+            .route("/mandelbrot", web::get().to_async(get_mandelbrot))
 
     });
 
@@ -111,6 +140,95 @@ fn post_gcd(form: web::Form<GcdParameters>) -> HttpResponse {
         .body(response)
 }
 
+// MANDELBROT TILE
+
+// The query parameters a client supplies to ask for a fractal tile: the
+// pixel dimensions of the image and the complex-plane viewport it should
+// cover. `limit` is optional since most requests are happy with a default
+// iteration count.
+#[derive(Deserialize)]
+struct TileParams {
+    width: u32,
+    height: u32,
+    re_min: f64,
+    im_min: f64,
+    re_max: f64,
+    im_max: f64,
+    limit: Option<usize>,
+}
+
+// Render a Mandelbrot tile on demand and return it as a PNG. Reuses the same
+// `render::render` the standalone `mandelbrot` program calls, just encoding
+// to an in-memory buffer instead of a file, since there's no filesystem path
+// to write to here.
+//
+// The validation below can reject a request immediately, but a request that
+// passes it still has to run real escape-time work. `web::block` hands that
+// work to Actix's blocking thread pool instead of the worker thread handling
+// every other request, so a few expensive tiles in flight at once can't
+// starve the rest of the server the way running `render::render` inline
+// would.
+fn get_mandelbrot(params: web::Query<TileParams>) -> impl Future<Item = HttpResponse, Error = Error> {
+
+    // Reject tile sizes that are empty or big enough to be a memory-exhaustion
+    // vector before we ever allocate a pixel buffer for them.
+    if params.width == 0 || params.height == 0
+        || params.width > MAX_TILE_DIMENSION || params.height > MAX_TILE_DIMENSION
+    {
+        return Either::A(future::ok(
+            HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(format!("width and height must be between 1 and {}", MAX_TILE_DIMENSION)),
+        ));
+    }
+
+    // Same reasoning for the iteration limit: an unbounded `limit` lets a
+    // client buy an arbitrary amount of CPU time per pixel.
+    if let Some(limit) = params.limit {
+        if limit == 0 || limit > MAX_ITERATION_LIMIT {
+            return Either::A(future::ok(
+                HttpResponse::BadRequest()
+                    .content_type("text/html")
+                    .body(format!("limit must be between 1 and {}", MAX_ITERATION_LIMIT)),
+            ));
+        }
+    }
+
+    let bounds = (params.width as usize, params.height as usize);
+    let limit = params.limit.unwrap_or(255);
+
+    // Cap the combined cost, not just each dimension independently: see
+    // MAX_RENDER_WORK for why the per-dimension caps alone aren't enough.
+    let work = bounds.0 as u64 * bounds.1 as u64 * limit as u64;
+    if work > MAX_RENDER_WORK {
+        return Either::A(future::ok(
+            HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(format!("width * height * limit must not exceed {}", MAX_RENDER_WORK)),
+        ));
+    }
+
+    let upper_left = Complex { re: params.re_min, im: params.im_max };
+    let lower_right = Complex { re: params.re_max, im: params.im_min };
+
+    Either::B(
+        web::block(move || -> Result<Vec<u8>, std::io::Error> {
+            // Three (R, G, B) bytes per pixel
+            let mut pixels = vec![0u8; bounds.0 * bounds.1 * 3];
+            render::render(&mut pixels, bounds, upper_left, lower_right, render::FractalKind::Mandelbrot, limit);
+
+            // Encode into an in-memory buffer rather than a file, since we're
+            // handing the bytes straight back in the HTTP response body.
+            let mut bytes = Vec::new();
+            PNGEncoder::new(Cursor::new(&mut bytes))
+                .encode(&pixels, bounds.0 as u32, bounds.1 as u32, ColorType::RGB(8))?;
+            Ok(bytes)
+        })
+        .map(|bytes| HttpResponse::Ok().content_type("image/png").body(bytes))
+        .map_err(|_| actix_web::error::ErrorInternalServerError("error rendering Mandelbrot tile")),
+    )
+}
+
 // CALCULATE GREATEST COMMON DIVISOR
 fn gcd(mut n: u64, mut m: u64) -> u64 {
     assert!(n != 0 && m != 0);
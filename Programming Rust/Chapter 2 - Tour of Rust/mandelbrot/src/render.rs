@@ -0,0 +1,247 @@
+// ============================
+// MANDELBROT RENDERER
+//
+// The core pixel-by-pixel renderer, factored out of `main.rs` so the Actix
+// server in `actix-gcd` can include this exact file (see its `mod render`)
+// rather than keeping a second, hand-copied version that could drift out of
+// sync with this one.
+
+use num::Complex;
+use std::str::FromStr;
+
+// ============================
+// FRACTAL KIND
+
+/// The per-step update rule used while iterating `escape_time`.
+///
+/// `Mandelbrot` is the classic `z = z*z + c` fractal. `Mandelbrot3` raises `z` to
+/// the third power instead of squaring it, which gives the set three-fold rather
+/// than two-fold symmetry. `BurningShip` takes the absolute value of `z`'s
+/// components before squaring, folding the plane into the jagged, ship-like shape
+/// the variant is named after.
+///
+/// This file is shared with the `actix-gcd` server (see its `#[path]`-included
+/// `mod render`), which only ever renders `Mandelbrot` tiles today and so
+/// never constructs the other variants. `#[allow(dead_code)]` keeps that
+/// binary's build from emitting a "never constructed" warning for variants
+/// only the standalone CLI selects.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FractalKind {
+    Mandelbrot,
+    Mandelbrot3,
+    BurningShip,
+}
+
+impl FromStr for FractalKind {
+    type Err = ();
+
+    /// Parse a fractal name as given on the command line, e.g. `"mandelbrot"`,
+    /// `"mandelbrot3"` or `"burning_ship"`. Any other string is rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "mandelbrot3" => Ok(FractalKind::Mandelbrot3),
+            "burning_ship" => Ok(FractalKind::BurningShip),
+            _ => Err(()),
+        }
+    }
+}
+
+#[test]
+fn test_fractal_kind_from_str() {
+    assert_eq!("mandelbrot".parse(), Ok(FractalKind::Mandelbrot));
+    assert_eq!("mandelbrot3".parse(), Ok(FractalKind::Mandelbrot3));
+    assert_eq!("burning_ship".parse(), Ok(FractalKind::BurningShip));
+    assert_eq!("nonsense".parse::<FractalKind>(), Err(()));
+}
+
+// ============================
+// RENDER
+
+/// Render a rectangle of the Mandelbrot set into a buffer of pixels.
+///
+/// The `bounds` argument gives the width and height of the buffer `pixels`,
+/// which holds three (R, G, B) bytes per pixel. The `upper_left` and
+/// `lower_right` arguments specify points on the complex plane corresponding
+/// to the upper-left and lower-right corners of the pixel buffer. `limit`
+/// caps how many iterations `escape_time` will try per pixel.
+pub fn render(pixels: &mut [u8],
+               bounds: (usize, usize),
+               upper_left: Complex<f64>,
+               lower_right: Complex<f64>,
+               kind: FractalKind,
+               limit: usize)
+{
+    // Three color bytes per pixel
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    // Iterate through the one dimensional pixel array like we are moving
+    // through a grid
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+
+            // Map from pixel coordinates to imaginary coordinates
+            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+
+            // Points that never escape stay black. Points that do escape are colored
+            // by how smoothly they escaped, using the normalized (fractional)
+            // iteration count rather than the raw integer one, which is what
+            // eliminates the harsh color bands of a plain `255 - count` mapping.
+            let rgb = match escape_time(point, limit, kind) {
+                None => [0, 0, 0],
+                Some((count, z)) => {
+                    let mu = count as f64 + 1.0 - z.norm().ln().ln() / 2.0_f64.ln();
+                    color_for_mu(mu / limit as f64)
+                }
+            };
+
+            let offset = (row * bounds.0 + column) * 3;
+            pixels[offset..offset + 3].copy_from_slice(&rgb);
+
+        }
+    }
+}
+
+// ============================
+// PALETTE
+
+/// Map a normalized escape value `t` (typically in `0.0..=1.0`, though values
+/// can run slightly past 1.0 near the escape boundary) to an RGB color by
+/// sweeping through hue space. `t = 0.0` sits at the start of the sweep and
+/// `t = 1.0` has gone all the way around, so nearby escape counts get visibly
+/// distinct, smoothly blending colors instead of abrupt gray bands.
+fn color_for_mu(t: f64) -> [u8; 3] {
+    let hue = (t.fract().abs() * 360.0) % 360.0;
+    hsv_to_rgb(hue, 0.7, 1.0)
+}
+
+/// Convert an HSV color (`hue` in degrees `0.0..360.0`, `saturation` and
+/// `value` in `0.0..=1.0`) to eight-bit RGB bytes.
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [u8; 3] {
+    let c = value * saturation;
+    let h_prime = hue / 60.0;
+    let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
+    let (r1, g1, b1) = match h_prime as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    [
+        ((r1 + m) * 255.0).round() as u8,
+        ((g1 + m) * 255.0).round() as u8,
+        ((b1 + m) * 255.0).round() as u8,
+    ]
+}
+
+// ============================
+// PIXELS TO COMPLEX NUMBERS MAP
+
+/// Given the row and column of a pixel in the output image, return the
+/// corresponding point on the complex plane.
+///
+/// `bounds` is a pair giving the width and height of the image in pixels.
+/// `pixel` is a (column, row) pair indicating a particular pixel in that image.
+/// The `upper_left` and `lower_right` parameters are points on the complex
+/// plane designating the area our image covers.
+
+pub fn pixel_to_point(bounds: (usize, usize), pixel: (usize, usize),
+                       upper_left: Complex<f64>, lower_right: Complex<f64>) -> Complex<f64>
+{
+
+    // Width  = X right-most coordinate - X left-most coordinate
+    // Height = Y top coordinate - Y bottom coordinate
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+
+    Complex{
+        re: upper_left.re + pixel.0 as f64 * width / bounds.0 as f64,
+
+        // Why a subtraction here? Pixel.1 increases as we go down, but the imaginary
+        // component increases as we go up.
+        im: upper_left.im - pixel.1 as f64 * height / bounds.1 as f64
+    }
+
+}
+
+#[test]
+fn test_pixel_to_point() {
+    assert_eq!(pixel_to_point((100, 100), (25, 75),
+    Complex { re: -1.0, im: 1.0 },
+    Complex { re: 1.0, im: -1.0 }),
+    Complex { re: -0.5, im: -0.5 });
+}
+
+// ============================
+// ESCAPE TIME
+
+/// Try to determine if `c` is in the Mandelbrot set, using at most `limit`
+/// iterations to decide.
+///
+/// If `c` is not a member, return `Some((i, z))`, where `i` is the number of
+/// iterations it took for `c` to leave the circle of radius two centered on the
+/// origin, and `z` is the value that escaped. Callers use `z` to compute a
+/// smooth, fractional iteration count instead of banding on the integer `i`.
+/// If `c` seems to be a member (more precisely, if we reached the iteration
+/// limit without being able to prove that `c` is not a member), return
+/// `None`. The `kind` argument selects which formula is iterated; see
+/// `FractalKind`.
+fn escape_time(c: Complex<f64>, limit: usize, kind: FractalKind) -> Option<(usize, Complex<f64>)> {
+
+    // Its traditional to use "z" for complex numbers.
+    // The crate's num "Complex" type is a struct defined as follows:
+    //
+    //   struct Complex<T> {
+    //     re: T,
+    //     im: T,
+    //   }
+    //
+    // This code defines a generic struct with two fields: re and im. It's generic
+    // because the "<T>" after the type name is read as "for any type T". Here we
+    // are intializing both the real and imaginary values of the complex value. The
+    // "num" crate makes sure that any default operation between numbers (-, +, *)
+    // is also valid for complex ones.
+    let mut z = Complex { re: 0.0, im: 0.0 };
+
+    // Iterate from 0 to (no including) the Limit
+    for i in 0..limit {
+
+        // We get the squared norm (X^2 + Y^2) and check if that distance is higher
+        // than 4 to check if "z" has left the circle of radius two.
+        if z.norm_sqr() > 4.0 {
+            return Some((i, z));
+        }
+
+        // Apply the update rule for the chosen fractal. "BurningShip" folds "z"
+        // into the first quadrant before squaring; the other variants square (or
+        // cube) "z" directly.
+        z = match kind {
+            FractalKind::Mandelbrot => z * z + c,
+            FractalKind::Mandelbrot3 => z * z * z + c,
+            FractalKind::BurningShip => {
+                let folded = Complex { re: z.re.abs(), im: z.im.abs() };
+                folded * folded + c
+            }
+        };
+    }
+
+    None
+}
+
+// NOTE: The function's return value is an Option<(usize, Complex<f64>)>. Rust's standard library defines the
+// "Option" type as follows:
+//
+//      enum Option<T> {
+//          None,
+//          Some(T),
+//      }
+//
+// "Option" is an enum, because its definition enumerates several variants that a value of
+// this type could be: "For any type T, a value of type Option is either Some(v), where v
+// is a value of type T; or None, indicating no value is available". In this case, "escape_time"
+// returns an "Option" to indicate whether "c" is in the Mandelbrot set, and if it's not, how
+// long we had to iterate to find that out, along with the escaping value of "z"
+// (returning "Some((i, z))").